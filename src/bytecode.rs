@@ -0,0 +1,136 @@
+//! The bytecode format shared by [`crate::vm::VM`] and [`crate::disasm`].
+//!
+//! An [`Opcode`] is a single byte; some opcodes are followed by little-endian operand bytes
+//! (e.g. `Jump`'s two-byte target). [`Instruction`] pairs a decoded opcode with its operand.
+
+use alloc::vec::Vec;
+
+/// A compiled program: a flat byte buffer of opcodes interleaved with their operands.
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    pub instructions: Vec<u8>,
+}
+
+impl Bytecode {
+    pub fn new(instructions: Vec<u8>) -> Self {
+        Bytecode { instructions }
+    }
+}
+
+/// The opcode byte an instruction starts with, before its operands (if any) are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Opcode {
+    Halt = 0,
+    Jump = 1,
+    Jnz = 2,
+    Jumpi = 3,
+    Dup = 4,
+    Swap = 5,
+    Drop = 6,
+    Push4 = 7,
+    Push2 = 8,
+    Push1 = 9,
+    Add = 10,
+    Sub = 11,
+    Mul = 12,
+    Div = 13,
+    Mod = 14,
+    Eq = 15,
+    Ne = 16,
+    Lt = 17,
+    Gt = 18,
+    Le = 19,
+    Ge = 20,
+    Not = 21,
+    And = 22,
+    Or = 23,
+    Input = 24,
+    Output = 25,
+    Alloc = 26,
+    Load = 27,
+    Clock = 28,
+}
+
+impl Opcode {
+    /// Decode an opcode byte, or `None` if it doesn't correspond to any known opcode.
+    pub fn from_u8(byte: u8) -> Option<Opcode> {
+        Some(match byte {
+            0 => Opcode::Halt,
+            1 => Opcode::Jump,
+            2 => Opcode::Jnz,
+            3 => Opcode::Jumpi,
+            4 => Opcode::Dup,
+            5 => Opcode::Swap,
+            6 => Opcode::Drop,
+            7 => Opcode::Push4,
+            8 => Opcode::Push2,
+            9 => Opcode::Push1,
+            10 => Opcode::Add,
+            11 => Opcode::Sub,
+            12 => Opcode::Mul,
+            13 => Opcode::Div,
+            14 => Opcode::Mod,
+            15 => Opcode::Eq,
+            16 => Opcode::Ne,
+            17 => Opcode::Lt,
+            18 => Opcode::Gt,
+            19 => Opcode::Le,
+            20 => Opcode::Ge,
+            21 => Opcode::Not,
+            22 => Opcode::And,
+            23 => Opcode::Or,
+            24 => Opcode::Input,
+            25 => Opcode::Output,
+            26 => Opcode::Alloc,
+            27 => Opcode::Load,
+            28 => Opcode::Clock,
+            _ => return None,
+        })
+    }
+}
+
+/// A fully decoded instruction: an [`Opcode`] together with its operand, if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Halt,
+    /// Unconditional jump to an absolute byte address.
+    Jump(u16),
+    /// Pop an int; jump to an absolute byte address if it's non-zero.
+    Jnz(u16),
+    /// Pop an int and jump to it as an absolute byte address.
+    Jumpi,
+    /// Push a copy of the stack slot `depth` below the top.
+    Dup(u8),
+    /// Swap the top of the stack with the slot `depth` below it.
+    Swap(u8),
+    /// Pop and discard the top of the stack.
+    Drop,
+    Push4(i32),
+    Push2(i16),
+    Push1(i8),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Not,
+    And,
+    Or,
+    /// Read one byte from the host and push it as an int.
+    Input,
+    /// Pop an int, treat it as a Unicode scalar value, and write it to the host.
+    Output,
+    /// Pop a tag and a size, then allocate a heap block from the top `size` stack words.
+    Alloc,
+    /// Pop a pointer and push the heap word at `pointer + offset`.
+    Load(u32),
+    /// Push the host's wall-clock reading, formatted, to the host's output.
+    Clock,
+}
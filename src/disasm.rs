@@ -0,0 +1,163 @@
+//! A standalone disassembler for [`Bytecode`] programs.
+//!
+//! Unlike `VM::next_instr`, which decodes one instruction at a time while executing, `disasm`
+//! walks an entire program up front and produces a full listing. This is useful for debugging
+//! compiled output without running it.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::bytecode::{Bytecode, Instruction, Opcode};
+
+/// A single decoded slot in a disassembly listing, tagged with the byte offset it starts at.
+#[derive(Debug, Clone)]
+pub enum DisasmEntry {
+    /// A successfully decoded instruction.
+    Instr(Instruction),
+    /// A byte that doesn't correspond to any known [`Opcode`].
+    InvalidOpcode(u8),
+    /// An instruction whose operand bytes run past the end of the buffer.
+    Truncated,
+}
+
+/// Decode every instruction in `bytecode`, from offset 0 to the end of the buffer.
+///
+/// Each entry is paired with the byte address it starts at, so jump targets can be matched back
+/// up against the listing. Decoding never panics: an unrecognized opcode byte or an instruction
+/// whose operands run past the end of the buffer is recorded as an entry rather than aborting,
+/// and the latter ends the listing since there's nothing left to resynchronize on.
+pub fn disasm(bytecode: &Bytecode) -> Vec<(usize, DisasmEntry)> {
+    let code = &bytecode.instructions;
+    let mut out = Vec::new();
+    let mut pos = 0;
+
+    while pos < code.len() {
+        let addr = pos;
+        let opcode_byte = code[pos];
+        pos += 1;
+
+        let entry = match Opcode::from_u8(opcode_byte) {
+            Some(opcode) => match decode_operands(opcode, code, &mut pos) {
+                Some(instr) => DisasmEntry::Instr(instr),
+                None => {
+                    out.push((addr, DisasmEntry::Truncated));
+                    break;
+                }
+            },
+            None => DisasmEntry::InvalidOpcode(opcode_byte),
+        };
+        out.push((addr, entry));
+    }
+
+    out
+}
+
+/// Decode the operand bytes for `opcode`, advancing `pos`. Returns `None` if the buffer runs out
+/// partway through, mirroring the shape of `VM::next_instr` (which reports that as `Trap::Truncated`).
+fn decode_operands(opcode: Opcode, code: &[u8], pos: &mut usize) -> Option<Instruction> {
+    fn next_byte(code: &[u8], pos: &mut usize) -> Option<u8> {
+        let byte = *code.get(*pos)?;
+        *pos += 1;
+        Some(byte)
+    }
+
+    Some(match opcode {
+        Opcode::Halt => Instruction::Halt,
+        Opcode::Jump => {
+            let addr = [next_byte(code, pos)?, next_byte(code, pos)?];
+            Instruction::Jump(u16::from_le_bytes(addr))
+        }
+        Opcode::Jnz => {
+            let addr = [next_byte(code, pos)?, next_byte(code, pos)?];
+            Instruction::Jnz(u16::from_le_bytes(addr))
+        }
+        Opcode::Jumpi => Instruction::Jumpi,
+        Opcode::Dup => Instruction::Dup(next_byte(code, pos)?),
+        Opcode::Swap => Instruction::Swap(next_byte(code, pos)?),
+        Opcode::Drop => Instruction::Drop,
+        Opcode::Push4 => {
+            let arg = [
+                next_byte(code, pos)?,
+                next_byte(code, pos)?,
+                next_byte(code, pos)?,
+                next_byte(code, pos)?,
+            ];
+            Instruction::Push4(i32::from_le_bytes(arg))
+        }
+        Opcode::Push2 => {
+            let arg = [next_byte(code, pos)?, next_byte(code, pos)?];
+            Instruction::Push2(i16::from_le_bytes(arg))
+        }
+        Opcode::Push1 => Instruction::Push1(next_byte(code, pos)? as i8),
+        Opcode::Add => Instruction::Add,
+        Opcode::Sub => Instruction::Sub,
+        Opcode::Mul => Instruction::Mul,
+        Opcode::Div => Instruction::Div,
+        Opcode::Mod => Instruction::Mod,
+        Opcode::Eq => Instruction::Eq,
+        Opcode::Ne => Instruction::Ne,
+        Opcode::Lt => Instruction::Lt,
+        Opcode::Gt => Instruction::Gt,
+        Opcode::Le => Instruction::Le,
+        Opcode::Ge => Instruction::Ge,
+        Opcode::Not => Instruction::Not,
+        Opcode::And => Instruction::And,
+        Opcode::Or => Instruction::Or,
+        Opcode::Input => Instruction::Input,
+        Opcode::Output => Instruction::Output,
+        Opcode::Alloc => Instruction::Alloc,
+        Opcode::Load => {
+            let addr = [
+                next_byte(code, pos)?,
+                next_byte(code, pos)?,
+                next_byte(code, pos)?,
+                next_byte(code, pos)?,
+            ];
+            Instruction::Load(u32::from_le_bytes(addr))
+        }
+        Opcode::Clock => Instruction::Clock,
+    })
+}
+
+/// Pretty-printer for the output of [`disasm`].
+///
+/// Wraps a listing so it can be formatted with `{}`. `Jump`/`Jnz` targets are checked against the
+/// listing's own entry addresses and flagged when they don't land on one, since that means the
+/// target either falls outside the buffer or jumps into the middle of another instruction.
+pub struct Disassembly<'a>(pub &'a [(usize, DisasmEntry)]);
+
+impl Disassembly<'_> {
+    /// Whether `addr` is the start of a decoded entry in this listing, i.e. a valid jump target.
+    fn is_valid_target(&self, addr: u16) -> bool {
+        self.0.iter().any(|(a, _)| *a == addr as usize)
+    }
+
+    fn write_jump(&self, f: &mut fmt::Formatter<'_>, addr: usize, name: &str, target: u16) -> fmt::Result {
+        write!(f, "{addr:#06x}: {name} {target:#06x}")?;
+        if !self.is_valid_target(target) {
+            write!(f, " -> invalid target")?;
+        }
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (addr, entry) in self.0 {
+            match entry {
+                DisasmEntry::Instr(Instruction::Jump(target)) => {
+                    self.write_jump(f, *addr, "Jump", *target)?
+                }
+                DisasmEntry::Instr(Instruction::Jnz(target)) => {
+                    self.write_jump(f, *addr, "Jnz", *target)?
+                }
+                DisasmEntry::Instr(instr) => writeln!(f, "{addr:#06x}: {instr:?}")?,
+                DisasmEntry::InvalidOpcode(byte) => {
+                    writeln!(f, "{addr:#06x}: <invalid opcode 0x{byte:02x}>")?
+                }
+                DisasmEntry::Truncated => writeln!(f, "{addr:#06x}: <truncated instruction>")?,
+            }
+        }
+        Ok(())
+    }
+}
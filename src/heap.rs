@@ -1,5 +1,8 @@
-use std::collections::VecDeque;
-use std::fmt::{self, Debug};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug};
 
 #[derive(Clone, Copy)]
 pub struct Word {
@@ -9,7 +12,7 @@ pub struct Word {
 impl Word {
     pub fn from_pointer(ptr: usize) -> Word {
         Word {
-            w: (ptr as i32) << 1 | 0,
+            w: (ptr as i32) << 1,
         }
     }
 
@@ -40,6 +43,10 @@ impl Debug for Word {
     }
 }
 
+/// A garbage collection cycle could not reclaim enough room in the to-space for the live set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfMemory;
+
 #[derive(Debug)]
 pub struct Heap {
     pub heap: Box<[Word]>,
@@ -61,7 +68,31 @@ impl Heap {
         }
     }
 
-    pub fn gc<'a>(&mut self, rootset: impl IntoIterator<Item = &'a mut Word>) {
+    /// Collect garbage, then scrub the dead from-space and the unused tail of the to-space back
+    /// to zero. This is the default: it costs an extra pass over the dead region, but means freed
+    /// words don't keep leaking prior objects' contents into heap dumps or confusing a
+    /// conservative rootset scan. Callers that care more about raw speed than that can use
+    /// [`Heap::gc_no_scrub`] instead.
+    pub fn gc<'a>(
+        &mut self,
+        rootset: impl IntoIterator<Item = &'a mut Word>,
+    ) -> Result<(), OutOfMemory> {
+        self.gc_impl(rootset, true)
+    }
+
+    /// Like [`Heap::gc`], but skips zeroing the dead from-space and the unused to-space tail.
+    pub fn gc_no_scrub<'a>(
+        &mut self,
+        rootset: impl IntoIterator<Item = &'a mut Word>,
+    ) -> Result<(), OutOfMemory> {
+        self.gc_impl(rootset, false)
+    }
+
+    fn gc_impl<'a>(
+        &mut self,
+        rootset: impl IntoIterator<Item = &'a mut Word>,
+        scrub: bool,
+    ) -> Result<(), OutOfMemory> {
         /// This is our todo list for all the words to to be considered by the garbage collection
         /// algorithm. It can be either a word in the stack of the VM or a slot in the heap. We
         /// can't store a mutable Rust pointer to the heap slot since that prevents us from doing
@@ -71,15 +102,48 @@ impl Heap {
             StackWord(&'a mut Word),
             HeapSlot(usize),
         }
-        let mut todo = VecDeque::new();
-        todo.extend(rootset.into_iter().map(TodoEntry::StackWord));
+        let roots: Vec<&'a mut Word> = rootset.into_iter().collect();
 
         let pivot = self.heap.len() / 2;
-        let (from_range, mut next, limit) = match self.first_half_active {
+        let (from_range, next_start, limit) = match self.first_half_active {
             true => (0..pivot, pivot, self.heap.len()),
             false => (pivot..self.heap.len(), 0, pivot),
         };
+
+        // Walk the live set read-only first, to find out whether it fits the to-space, before
+        // mutating anything. If we find out partway through the real copy below that the live
+        // set doesn't fit, there's no way back: some objects are already evacuated and some
+        // roots already repointed at the to-space, so the heap would be left half-migrated and
+        // unusable. Paying for a second traversal keeps a failed collection a no-op, so the
+        // caller can `grow` and retry against an untouched heap.
+        let mut seen = BTreeSet::new();
+        let mut walk: VecDeque<usize> = roots
+            .iter()
+            .map(|word| word.to_pointer())
+            .filter(|ptr| from_range.contains(ptr))
+            .collect();
+        let mut needed = 0usize;
+        while let Some(ptr) = walk.pop_front() {
+            if !seen.insert(ptr) {
+                continue;
+            }
+            let header = self.heap[ptr].to_int();
+            let size = (header >> 8) as usize;
+            needed += size + 1;
+            for word in &self.heap[ptr + 1..ptr + 1 + size] {
+                if word.is_pointer() && from_range.contains(&word.to_pointer()) {
+                    walk.push_back(word.to_pointer());
+                }
+            }
+        }
+        if needed > limit - next_start {
+            return Err(OutOfMemory);
+        }
+
         self.first_half_active = !self.first_half_active;
+        let mut next = next_start;
+        let mut todo = VecDeque::new();
+        todo.extend(roots.into_iter().map(TodoEntry::StackWord));
 
         // Loop invariants:
         //  - `from_range` contains the pointer range of the from-space
@@ -87,7 +151,7 @@ impl Heap {
         //  - `limit` contains one-past the last free slot of the to-space
         while let Some(entry) = todo.pop_front() {
             let word = match &entry {
-                TodoEntry::StackWord(word) => &*word,
+                TodoEntry::StackWord(word) => word,
                 TodoEntry::HeapSlot(ptr) => &self.heap[*ptr],
             };
             let ptr = word.to_pointer();
@@ -96,7 +160,7 @@ impl Heap {
                 if !self.heap[ptr].is_pointer() {
                     let header = self.heap[ptr].to_int();
                     let size = (header >> 8) as usize;
-                    assert!(next + size < limit, "GC out of memory");
+                    debug_assert!(next + size < limit, "live set size was checked to fit above");
 
                     // Copy the block to the to-space and set the forwarding pointer
                     self.heap.copy_within(ptr..ptr + size + 1, next);
@@ -119,6 +183,83 @@ impl Heap {
                 }
             }
         }
+
+        if scrub {
+            // The from-space holds only dead objects at this point (everything live was just
+            // copied out), and the to-space beyond `next` was never written to this cycle. Both
+            // still hold stale pointers/integers from prior allocations, so zero them out.
+            self.heap[from_range].fill(Word { w: 0 });
+            self.heap[next..limit].fill(Word { w: 0 });
+        }
+
+        self.free_addr = next;
+        Ok(())
+    }
+
+    /// Replace the backing buffer with a larger one, evacuating the live set (reachable from
+    /// `rootset`) out of the current active semi-space into the first half of the new buffer.
+    ///
+    /// This reuses the same Cheney-style forwarding-pointer copy as `gc`, just writing into a
+    /// fresh, bigger allocation instead of the other semi-space of the existing one. After
+    /// `grow`, the first half of the new buffer is active, exactly as after a fresh `Heap::new`.
+    pub fn grow<'a>(&mut self, new_total_words: usize, rootset: impl IntoIterator<Item = &'a mut Word>) {
+        assert!(
+            new_total_words > self.heap.len(),
+            "grow must increase the heap size"
+        );
+
+        #[derive(Debug)]
+        enum TodoEntry<'a> {
+            StackWord(&'a mut Word),
+            NewHeapSlot(usize),
+        }
+        let mut todo = VecDeque::new();
+        todo.extend(rootset.into_iter().map(TodoEntry::StackWord));
+
+        let pivot = self.heap.len() / 2;
+        let from_range = match self.first_half_active {
+            true => 0..pivot,
+            false => pivot..self.heap.len(),
+        };
+
+        let mut new_heap = vec![Word { w: 0 }; new_total_words].into_boxed_slice();
+        let limit = new_total_words / 2;
+        let mut next = 0;
+
+        while let Some(entry) = todo.pop_front() {
+            let word = match &entry {
+                TodoEntry::StackWord(word) => &**word,
+                TodoEntry::NewHeapSlot(ptr) => &new_heap[*ptr],
+            };
+            let ptr = word.to_pointer();
+            if from_range.contains(&ptr) {
+                // Copy the block into the new heap if it's not a forwarding pointer yet. The
+                // forwarding pointer is recorded back into the *old* heap, which we still have
+                // mutable access to until `self.heap` is replaced below.
+                if !self.heap[ptr].is_pointer() {
+                    let header = self.heap[ptr].to_int();
+                    let size = (header >> 8) as usize;
+                    assert!(next + size < limit, "heap too small to fit live set after growth");
+
+                    new_heap[next..next + size + 1].copy_from_slice(&self.heap[ptr..ptr + size + 1]);
+                    self.heap[ptr] = Word::from_pointer(next);
+
+                    for (offset, word) in new_heap[next + 1..next + 1 + size].iter().enumerate() {
+                        if word.is_pointer() && from_range.contains(&word.to_pointer()) {
+                            todo.push_back(TodoEntry::NewHeapSlot(next + 1 + offset));
+                        }
+                    }
+                    next += size + 1;
+                }
+                match entry {
+                    TodoEntry::StackWord(word) => *word = self.heap[ptr],
+                    TodoEntry::NewHeapSlot(slot) => new_heap[slot] = self.heap[ptr],
+                }
+            }
+        }
+
+        self.heap = new_heap;
+        self.first_half_active = true;
         self.free_addr = next;
     }
 
@@ -0,0 +1,63 @@
+//! The VM's interface to the outside world.
+//!
+//! `VM` is generic over a [`Host`] instead of calling into `std::io`/`std::time` directly, so it
+//! can be embedded on targets that have neither (a kernel, a bare-metal target, ...). The crate
+//! itself only needs `alloc`; `std` is a default feature that additionally provides [`StdHost`],
+//! a convenience implementation backed by stdin/stdout and the wall clock.
+
+/// Everything a [`crate::vm::VM`] needs from its environment: byte input, character output, and
+/// a clock for the `Clock` opcode.
+pub trait Host {
+    /// Read one byte from the input stream, or `None` if none is available.
+    fn read_byte(&mut self) -> Option<u8>;
+    /// Write one character to the output stream.
+    fn write_char(&mut self, c: char);
+    /// Seconds elapsed since some host-defined epoch (e.g. process start).
+    fn clock(&self) -> f64;
+}
+
+/// The default [`Host`], backed by `std::io::stdin`/`print!`/`std::time::Instant`.
+#[cfg(feature = "std")]
+pub struct StdHost {
+    stdin: std::io::Stdin,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdHost {
+    pub fn new() -> Self {
+        StdHost {
+            stdin: std::io::stdin(),
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdHost {
+    fn default() -> Self {
+        StdHost::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Host for StdHost {
+    fn read_byte(&mut self) -> Option<u8> {
+        use std::io::Read;
+
+        let mut byte = 0u8;
+        self.stdin
+            .lock()
+            .read_exact(std::slice::from_mut(&mut byte))
+            .ok()?;
+        Some(byte)
+    }
+
+    fn write_char(&mut self, c: char) {
+        print!("{c}");
+    }
+
+    fn clock(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
@@ -0,0 +1,14 @@
+//! An embeddable bytecode interpreter: no OS or allocator assumptions beyond `alloc`.
+//!
+//! The `std` feature (on by default) additionally provides [`host::StdHost`], a convenience
+//! [`host::Host`] backed by stdin/stdout and the wall clock. Without it, the crate is
+//! `#![no_std]` and callers supply their own `Host`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod bytecode;
+pub mod disasm;
+pub mod heap;
+pub mod host;
+pub mod vm;
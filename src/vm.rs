@@ -1,14 +1,80 @@
-use std::io::Read;
-use std::time::Instant;
-
 use crate::bytecode::{Bytecode, Instruction, Opcode};
 use crate::heap::{Heap, Word};
+use crate::host::Host;
+#[cfg(feature = "std")]
+use crate::host::StdHost;
 
 pub const STACK_SIZE: usize = 1 << 14;
 pub const HEAP_SIZE: usize = 1 << 20;
 
+/// The kind of value a [`Word`] holds, used to describe the operand a trap expected versus what
+/// it actually found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordKind {
+    Int,
+    Pointer,
+}
+
+impl WordKind {
+    fn of(word: Word) -> WordKind {
+        if word.is_pointer() {
+            WordKind::Pointer
+        } else {
+            WordKind::Int
+        }
+    }
+}
+
+/// Why `VM::run` stopped running without reaching a normal `Halt`.
+///
+/// Unlike a panic, a trap leaves the VM's state (`ip`, `stack_ptr`, `heap`, ...) intact, so host
+/// code can inspect it, report a diagnostic, or in some cases resume execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trap {
+    /// The byte at `ip` doesn't correspond to any known [`Opcode`].
+    InvalidOpcode(u8),
+    /// An instruction received an operand of the wrong kind (int vs. pointer).
+    TypeMismatch { expected: WordKind, got: WordKind },
+    /// `Div` or `Mod` with a zero divisor.
+    DivisionByZero,
+    /// `Div` or `Mod` overflowed (`i32::MIN / -1` or `i32::MIN % -1`).
+    ArithmeticOverflow,
+    /// `push_word` would write past `STACK_SIZE`.
+    StackOverflow,
+    /// `pop_word`/`peek_word` was called with nothing left to pop/peek.
+    StackUnderflow,
+    /// An instruction's operand, immediate or popped from the stack, is out of range for what it
+    /// expects (e.g. an `Alloc` tag or size that doesn't fit its target type, or a `Swap` depth
+    /// of 0).
+    InvalidOperand,
+    /// `ip` ran off the end of the bytecode buffer while decoding an opcode or its operands.
+    Truncated,
+    /// `Load`'s `pointer + offset` overflowed or landed outside the heap buffer.
+    HeapOutOfBounds,
+    /// The heap could not satisfy an `Alloc` even after a GC cycle and growing the heap.
+    HeapExhausted,
+    /// An I/O operation with the host failed.
+    IoError,
+}
+
+/// The VM stopped because it executed a `Halt` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Halt;
+
+/// The outcome of a single [`VM::run_steps`] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    /// The VM executed a `Halt` instruction.
+    Halted,
+    /// The budget ran out before the VM halted or trapped. `ip`/`stack_ptr`/`heap` are untouched,
+    /// so the caller can resume with another `run_steps` call.
+    BudgetExhausted,
+    /// The VM trapped; see [`Trap`] for why.
+    Trap(Trap),
+}
+
 /// The VM struct
-pub struct VM {
+pub struct VM<H: Host> {
     pub bytecode: Bytecode,
     /// Fixed-size stack of words
     stack: [Word; STACK_SIZE],
@@ -18,21 +84,44 @@ pub struct VM {
     ip: usize,
     /// The heap
     heap: Heap,
+    /// The VM's interface to the outside world (I/O, clock).
+    host: H,
+    /// Number of instructions executed so far, across all `run`/`run_steps` calls.
+    cycles: u64,
 }
 
-impl VM {
-    /// Create a new `VM` with the given bytecode
+#[cfg(feature = "std")]
+impl VM<StdHost> {
+    /// Create a new `VM` with the given bytecode, using the standard library's stdin/stdout and
+    /// wall clock as its host.
     pub fn new(bytecode: Bytecode) -> Self {
+        VM::with_host(bytecode, StdHost::new())
+    }
+}
+
+impl<H: Host> VM<H> {
+    /// Create a new `VM` with the given bytecode and host.
+    pub fn with_host(bytecode: Bytecode, host: H) -> Self {
         VM {
             bytecode,
             stack: [Word::from_int(0); STACK_SIZE], // Initialize stack with zeroes
             stack_ptr: 0,
             ip: 0,
             heap: Heap::new(HEAP_SIZE), // The heap
+            host,
+            cycles: 0,
         }
     }
 
-    fn print_state(&mut self) {
+    /// Number of instructions executed so far, across all `run`/`run_steps` calls. Host code can
+    /// use this for time-slicing, watchdog limits, or fairly multiplexing several VM instances.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Print the current stack and the instruction about to execute, for interactive debugging.
+    #[cfg(feature = "std")]
+    pub fn print_state(&mut self) {
         print!("Stack: ");
         for i in 0..self.stack_ptr {
             print!("| {:?} ", self.stack[i]);
@@ -44,41 +133,43 @@ impl VM {
         println!("IP 0x{:X}: {:?}", self.ip, opcode);
     }
 
-    fn next_byte(&mut self) -> u8 {
-        let byte = self.bytecode.instructions[self.ip];
+    fn next_byte(&mut self) -> Result<u8, Trap> {
+        let byte = *self.bytecode.instructions.get(self.ip).ok_or(Trap::Truncated)?;
         self.ip += 1;
-        byte
+        Ok(byte)
     }
 
-    fn next_instr(&mut self) -> Instruction {
-        match Opcode::from_u8(self.next_byte()).expect("Invalid bytecode") {
+    fn next_instr(&mut self) -> Result<Instruction, Trap> {
+        let opcode_byte = self.next_byte()?;
+        let opcode = Opcode::from_u8(opcode_byte).ok_or(Trap::InvalidOpcode(opcode_byte))?;
+        Ok(match opcode {
             Opcode::Halt => Instruction::Halt,
             Opcode::Jump => {
-                let addr = [self.next_byte(), self.next_byte()];
+                let addr = [self.next_byte()?, self.next_byte()?];
                 Instruction::Jump(u16::from_le_bytes(addr))
             }
             Opcode::Jnz => {
-                let addr = [self.next_byte(), self.next_byte()];
+                let addr = [self.next_byte()?, self.next_byte()?];
                 Instruction::Jnz(u16::from_le_bytes(addr))
             }
             Opcode::Jumpi => Instruction::Jumpi,
-            Opcode::Dup => Instruction::Dup(self.next_byte()),
-            Opcode::Swap => Instruction::Swap(self.next_byte()),
+            Opcode::Dup => Instruction::Dup(self.next_byte()?),
+            Opcode::Swap => Instruction::Swap(self.next_byte()?),
             Opcode::Drop => Instruction::Drop,
             Opcode::Push4 => {
                 let arg = [
-                    self.next_byte(),
-                    self.next_byte(),
-                    self.next_byte(),
-                    self.next_byte(),
+                    self.next_byte()?,
+                    self.next_byte()?,
+                    self.next_byte()?,
+                    self.next_byte()?,
                 ];
                 Instruction::Push4(i32::from_le_bytes(arg))
             }
             Opcode::Push2 => {
-                let arg = [self.next_byte(), self.next_byte()];
+                let arg = [self.next_byte()?, self.next_byte()?];
                 Instruction::Push2(i16::from_le_bytes(arg))
             }
-            Opcode::Push1 => Instruction::Push1(self.next_byte() as i8),
+            Opcode::Push1 => Instruction::Push1(self.next_byte()? as i8),
             Opcode::Add => Instruction::Add,
             Opcode::Sub => Instruction::Sub,
             Opcode::Mul => Instruction::Mul,
@@ -98,170 +189,364 @@ impl VM {
             Opcode::Alloc => Instruction::Alloc,
             Opcode::Load => {
                 let addr = [
-                    self.next_byte(),
-                    self.next_byte(),
-                    self.next_byte(),
-                    self.next_byte(),
+                    self.next_byte()?,
+                    self.next_byte()?,
+                    self.next_byte()?,
+                    self.next_byte()?,
                 ];
                 Instruction::Load(u32::from_le_bytes(addr))
             }
             Opcode::Clock => Instruction::Clock,
-        }
+        })
     }
 
-    fn peek_word(&self, depth: usize) -> Word {
-        self.stack[self.stack_ptr - 1 - depth]
+    fn peek_word(&self, depth: usize) -> Result<Word, Trap> {
+        self.stack_ptr
+            .checked_sub(1 + depth)
+            .map(|i| self.stack[i])
+            .ok_or(Trap::StackUnderflow)
     }
 
-    fn peek_word_mut(&mut self, depth: usize) -> &mut Word {
-        &mut self.stack[self.stack_ptr - 1 - depth]
+    fn peek_word_mut(&mut self, depth: usize) -> Result<&mut Word, Trap> {
+        let i = self.stack_ptr.checked_sub(1 + depth).ok_or(Trap::StackUnderflow)?;
+        Ok(&mut self.stack[i])
     }
 
-    fn pop_word(&mut self) -> Word {
-        let word = self.peek_word(0);
+    fn pop_word(&mut self) -> Result<Word, Trap> {
+        let word = self.peek_word(0)?;
         self.stack_ptr -= 1;
-        word
+        Ok(word)
     }
 
-    fn push_word(&mut self, w: Word) {
+    fn push_word(&mut self, w: Word) -> Result<(), Trap> {
+        if self.stack_ptr >= STACK_SIZE {
+            return Err(Trap::StackOverflow);
+        }
         self.stack[self.stack_ptr] = w;
         self.stack_ptr += 1;
+        Ok(())
+    }
+
+    fn expect_int(word: Word) -> Result<i32, Trap> {
+        if word.is_pointer() {
+            Err(Trap::TypeMismatch {
+                expected: WordKind::Int,
+                got: WordKind::of(word),
+            })
+        } else {
+            Ok(word.to_int())
+        }
+    }
+
+    fn expect_pointer(word: Word) -> Result<usize, Trap> {
+        if word.is_pointer() {
+            Ok(word.to_pointer())
+        } else {
+            Err(Trap::TypeMismatch {
+                expected: WordKind::Pointer,
+                got: WordKind::of(word),
+            })
+        }
     }
 
-    pub fn run(&mut self) {
-        let stdin = std::io::stdin();
-        let mut stdin = stdin.lock();
-        let start = Instant::now();
+    /// Run until `Halt` or a trap, with no limit on the number of instructions executed.
+    pub fn run(&mut self) -> Result<Halt, Trap> {
         loop {
-            match self.next_instr() {
-                Instruction::Halt => break,
-                Instruction::Jump(addr) => {
+            if let core::ops::ControlFlow::Break(halt) = self.step()? {
+                return Ok(halt);
+            }
+        }
+    }
+
+    /// Execute at most `budget` instructions, then return. `ip`/`stack_ptr`/`heap` are left
+    /// intact in every case, so [`StepResult::BudgetExhausted`] can simply be resumed with
+    /// another `run_steps` call, enabling cooperative scheduling of several VM instances.
+    pub fn run_steps(&mut self, budget: u64) -> StepResult {
+        for _ in 0..budget {
+            match self.step() {
+                Ok(core::ops::ControlFlow::Break(_)) => return StepResult::Halted,
+                Ok(core::ops::ControlFlow::Continue(())) => {}
+                Err(trap) => return StepResult::Trap(trap),
+            }
+        }
+        StepResult::BudgetExhausted
+    }
+
+    /// Execute a single instruction, returning `Break(Halt)` once `Halt` is reached.
+    fn step(&mut self) -> Result<core::ops::ControlFlow<Halt>, Trap> {
+        self.cycles += 1;
+        match self.next_instr()? {
+            Instruction::Halt => return Ok(core::ops::ControlFlow::Break(Halt)),
+            Instruction::Jump(addr) => {
+                self.ip = addr as usize;
+            }
+            Instruction::Jnz(addr) => {
+                let arg = self.pop_word()?;
+                if Self::expect_int(arg)? != 0 {
                     self.ip = addr as usize;
                 }
-                Instruction::Jnz(addr) => {
-                    let arg = self.pop_word();
-                    assert!(!arg.is_pointer());
-                    if arg.to_int() != 0 {
-                        self.ip = addr as usize;
-                    }
-                }
-                Instruction::Jumpi => {
-                    let addr = self.pop_word();
-                    assert!(!addr.is_pointer());
-                    self.ip = addr.to_int() as usize;
-                }
-                Instruction::Dup(depth) => {
-                    let arg = self.peek_word(depth as usize);
-                    self.push_word(arg);
-                }
-                Instruction::Swap(depth) => {
-                    assert!(depth > 0);
-                    let mut top = self.pop_word();
-                    std::mem::swap(&mut top, self.peek_word_mut((depth - 1) as usize));
-                    self.push_word(top);
-                }
-                Instruction::Drop => {
-                    self.pop_word();
-                }
-                Instruction::Push4(arg) => {
-                    self.push_word(Word::from_int(arg));
-                }
-                Instruction::Push2(arg) => {
-                    self.push_word(Word::from_int(arg as i32));
-                }
-                Instruction::Push1(arg) => {
-                    self.push_word(Word::from_int(arg as i32));
-                }
-                instr @ (Instruction::Add
-                | Instruction::Sub
-                | Instruction::Mul
-                | Instruction::Div
-                | Instruction::Mod
-                | Instruction::Eq
-                | Instruction::Ne
-                | Instruction::Lt
-                | Instruction::Gt
-                | Instruction::Le
-                | Instruction::Ge
-                | Instruction::And
-                | Instruction::Or) => {
-                    let b = self.pop_word();
-                    assert!(!b.is_pointer());
-                    let b = b.to_int();
-                    let a = self.pop_word();
-                    assert!(!a.is_pointer());
-                    let a = a.to_int();
-                    let result = match instr {
-                        Instruction::Add => a + b,
-                        Instruction::Sub => a - b,
-                        Instruction::Mul => a * b,
-                        Instruction::Div => a / b,
-                        Instruction::Mod => a % b,
-                        Instruction::Eq => (a == b) as i32,
-                        Instruction::Ne => (a != b) as i32,
-                        Instruction::Lt => (a < b) as i32,
-                        Instruction::Gt => (a > b) as i32,
-                        Instruction::Le => (a <= b) as i32,
-                        Instruction::Ge => (a >= b) as i32,
-                        Instruction::And => a & b,
-                        Instruction::Or => a | b,
-                        _ => unreachable!(),
-                    };
-                    self.push_word(Word::from_int(result));
+            }
+            Instruction::Jumpi => {
+                let addr = self.pop_word()?;
+                self.ip = Self::expect_int(addr)? as usize;
+            }
+            Instruction::Dup(depth) => {
+                let arg = self.peek_word(depth as usize)?;
+                self.push_word(arg)?;
+            }
+            Instruction::Swap(depth) => {
+                if depth == 0 {
+                    return Err(Trap::InvalidOperand);
                 }
-                Instruction::Not => {
-                    let arg = self.pop_word();
-                    assert!(!arg.is_pointer());
-                    self.push_word(Word::from_int((arg.to_int() == 0) as i32));
+                let mut top = self.pop_word()?;
+                core::mem::swap(&mut top, self.peek_word_mut((depth - 1) as usize)?);
+                self.push_word(top)?;
+            }
+            Instruction::Drop => {
+                self.pop_word()?;
+            }
+            Instruction::Push4(arg) => {
+                self.push_word(Word::from_int(arg))?;
+            }
+            Instruction::Push2(arg) => {
+                self.push_word(Word::from_int(arg as i32))?;
+            }
+            Instruction::Push1(arg) => {
+                self.push_word(Word::from_int(arg as i32))?;
+            }
+            instr @ (Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mul
+            | Instruction::Div
+            | Instruction::Mod
+            | Instruction::Eq
+            | Instruction::Ne
+            | Instruction::Lt
+            | Instruction::Gt
+            | Instruction::Le
+            | Instruction::Ge
+            | Instruction::And
+            | Instruction::Or) => {
+                let b = self.pop_word()?;
+                let b = Self::expect_int(b)?;
+                let a = self.pop_word()?;
+                let a = Self::expect_int(a)?;
+                if matches!(instr, Instruction::Div | Instruction::Mod) && b == 0 {
+                    return Err(Trap::DivisionByZero);
                 }
-                Instruction::Input => {
-                    let mut char: u8 = 0;
-                    stdin.read_exact(std::slice::from_mut(&mut char)).unwrap();
-                    self.push_word(Word::from_int(char as i32))
+                if matches!(instr, Instruction::Div | Instruction::Mod) && a == i32::MIN && b == -1 {
+                    return Err(Trap::ArithmeticOverflow);
                 }
-                Instruction::Output => {
-                    let arg = self.pop_word();
-                    let char = char::from_u32(u32::try_from(arg.to_int()).unwrap()).unwrap();
-                    print!("{char}");
+                let result = match instr {
+                    Instruction::Add => a + b,
+                    Instruction::Sub => a - b,
+                    Instruction::Mul => a * b,
+                    Instruction::Div => a / b,
+                    Instruction::Mod => a % b,
+                    Instruction::Eq => (a == b) as i32,
+                    Instruction::Ne => (a != b) as i32,
+                    Instruction::Lt => (a < b) as i32,
+                    Instruction::Gt => (a > b) as i32,
+                    Instruction::Le => (a <= b) as i32,
+                    Instruction::Ge => (a >= b) as i32,
+                    Instruction::And => a & b,
+                    Instruction::Or => a | b,
+                    _ => unreachable!(),
+                };
+                self.push_word(Word::from_int(result))?;
+            }
+            Instruction::Not => {
+                let arg = self.pop_word()?;
+                let arg = Self::expect_int(arg)?;
+                self.push_word(Word::from_int((arg == 0) as i32))?;
+            }
+            Instruction::Input => {
+                let byte = self.host.read_byte().ok_or(Trap::IoError)?;
+                self.push_word(Word::from_int(byte as i32))?;
+            }
+            Instruction::Output => {
+                let arg = self.pop_word()?;
+                let arg = Self::expect_int(arg)?;
+                let code = u32::try_from(arg).map_err(|_| Trap::IoError)?;
+                let char = char::from_u32(code).ok_or(Trap::IoError)?;
+                self.host.write_char(char);
+            }
+            Instruction::Alloc => {
+                let tag = self.pop_word()?;
+                let tag = Self::expect_int(tag)?;
+                let tag = u8::try_from(tag).map_err(|_| Trap::InvalidOperand)?;
+
+                let size = self.pop_word()?;
+                let size = Self::expect_int(size)?;
+                let size = usize::try_from(size).map_err(|_| Trap::InvalidOperand)?;
+
+                if size > self.stack_ptr {
+                    return Err(Trap::StackUnderflow);
                 }
-                Instruction::Alloc => {
-                    let tag = self.pop_word();
-                    assert!(!tag.is_pointer());
-                    let tag = u8::try_from(tag.to_int()).expect("invalid tag");
-
-                    let size = self.pop_word();
-                    assert!(!size.is_pointer());
-                    let size = usize::try_from(size.to_int()).expect("invalid size");
-
-                    assert!(size <= self.stack_ptr);
-                    let words = &self.stack[self.stack_ptr - size..self.stack_ptr];
-                    let pointer = match self.heap.alloc(size, tag, words) {
-                        Some(pointer) => pointer,
-                        None => {
-                            // Attempt a gc cycle
+                let words = &self.stack[self.stack_ptr - size..self.stack_ptr];
+                let pointer = match self.heap.alloc(size, tag, words) {
+                    Some(pointer) => pointer,
+                    None => {
+                        // Attempt a gc cycle. If the live set doesn't even fit the to-space,
+                        // `gc` leaves the heap untouched and reports `OutOfMemory` rather than
+                        // trapping outright: grow the heap and retry instead of giving up.
+                        let rootset = self.stack[..self.stack_ptr]
+                            .iter_mut()
+                            .filter(|w| w.is_pointer());
+                        if self.heap.gc(rootset).is_err() {
                             let rootset = self.stack[..self.stack_ptr]
                                 .iter_mut()
                                 .filter(|w| w.is_pointer());
-                            self.heap.gc(rootset);
-                            // Try to allocate again. This time failure is fatal.
-                            let words = &self.stack[self.stack_ptr - size..self.stack_ptr];
-                            self.heap
-                                .alloc(size, tag, words)
-                                .expect("allocation failed")
+                            self.heap.grow(self.heap.heap.len() * 2, rootset);
                         }
-                    };
-                    self.stack_ptr -= size;
-                    self.push_word(Word::from_pointer(pointer));
-                }
-                Instruction::Load(offset) => {
-                    let word = self.pop_word();
-                    assert!(word.is_pointer());
-                    self.push_word(self.heap.heap[word.to_pointer() + offset as usize]);
-                }
-                Instruction::Clock => {
-                    println!("{:.4}", start.elapsed().as_secs_f64());
+                        // Try to allocate again.
+                        let words = &self.stack[self.stack_ptr - size..self.stack_ptr];
+                        match self.heap.alloc(size, tag, words) {
+                            Some(pointer) => pointer,
+                            None => {
+                                // Still not enough room even after a collection (or a grow):
+                                // grow the heap and retry once more before giving up.
+                                let rootset = self.stack[..self.stack_ptr]
+                                    .iter_mut()
+                                    .filter(|w| w.is_pointer());
+                                self.heap.grow(self.heap.heap.len() * 2, rootset);
+                                let words = &self.stack[self.stack_ptr - size..self.stack_ptr];
+                                self.heap.alloc(size, tag, words).ok_or(Trap::HeapExhausted)?
+                            }
+                        }
+                    }
+                };
+                self.stack_ptr -= size;
+                self.push_word(Word::from_pointer(pointer))?;
+            }
+            Instruction::Load(offset) => {
+                let word = self.pop_word()?;
+                let ptr = Self::expect_pointer(word)?;
+                let addr = ptr.checked_add(offset as usize).ok_or(Trap::HeapOutOfBounds)?;
+                let value = *self.heap.heap.get(addr).ok_or(Trap::HeapOutOfBounds)?;
+                self.push_word(value)?;
+            }
+            Instruction::Clock => {
+                // `Clock` reports wall-clock time from the host rather than `self.cycles`:
+                // the cycle counter is a VM-internal scheduling aid, while this opcode is
+                // meant to let a guest program measure real elapsed time.
+                let now = self.host.clock();
+                #[cfg(feature = "std")]
+                let formatted = std::format!("{now:.4}\n");
+                #[cfg(not(feature = "std"))]
+                let formatted = alloc::format!("{now:.4}\n");
+                for c in formatted.chars() {
+                    self.host.write_char(c);
                 }
             }
         }
+        Ok(core::ops::ControlFlow::Continue(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// A [`Host`] that never produces input and discards output, for tests that don't exercise
+    /// `Input`/`Output`/`Clock`.
+    struct NullHost;
+
+    impl Host for NullHost {
+        fn read_byte(&mut self) -> Option<u8> {
+            None
+        }
+
+        fn write_char(&mut self, _c: char) {}
+
+        fn clock(&self) -> f64 {
+            0.0
+        }
+    }
+
+    fn vm(instructions: Vec<u8>) -> VM<NullHost> {
+        VM::with_host(Bytecode::new(instructions), NullHost)
+    }
+
+    fn push1(byte: i8) -> [u8; 2] {
+        [Opcode::Push1 as u8, byte as u8]
+    }
+
+    #[test]
+    fn division_by_zero_traps_and_leaves_state_inspectable() {
+        let mut vm = vm([push1(4).as_slice(), &push1(0), &[Opcode::Div as u8]].concat());
+        assert_eq!(vm.run(), Err(Trap::DivisionByZero));
+        // Both operands were popped before the trap, and `ip` sits right past the `Div` opcode.
+        assert_eq!(vm.stack_ptr, 0);
+        assert_eq!(vm.ip, 5);
+    }
+
+    #[test]
+    fn stack_underflow_traps_on_empty_stack() {
+        let mut vm = vm(alloc::vec![Opcode::Drop as u8]);
+        assert_eq!(vm.run(), Err(Trap::StackUnderflow));
+        assert_eq!(vm.stack_ptr, 0);
+        assert_eq!(vm.ip, 1);
+    }
+
+    #[test]
+    fn stack_overflow_traps_instead_of_panicking() {
+        // An infinite loop pushing a word each iteration eventually runs the stack out.
+        let bytes = [push1(0).as_slice(), &[Opcode::Jump as u8, 0, 0]].concat();
+        let mut vm = vm(bytes);
+        assert_eq!(vm.run(), Err(Trap::StackOverflow));
+        assert_eq!(vm.stack_ptr, STACK_SIZE);
+    }
+
+    #[test]
+    fn type_mismatch_traps_on_pointer_where_int_expected() {
+        // Alloc a zero-size block to get a pointer word, then try to use it as an int operand.
+        let bytes = [
+            push1(0).as_slice(), // size
+            &push1(0),           // tag
+            &[Opcode::Alloc as u8],
+            &push1(1),
+            &[Opcode::Add as u8],
+        ]
+        .concat();
+        let mut vm = vm(bytes);
+        assert_eq!(
+            vm.run(),
+            Err(Trap::TypeMismatch {
+                expected: WordKind::Int,
+                got: WordKind::Pointer,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_operand_traps_on_zero_depth_swap() {
+        let mut vm = vm(alloc::vec![Opcode::Swap as u8, 0]);
+        assert_eq!(vm.run(), Err(Trap::InvalidOperand));
+    }
+
+    #[test]
+    fn heap_out_of_bounds_traps_instead_of_panicking() {
+        let mut bytes = [push1(0).as_slice(), &push1(0), &[Opcode::Alloc as u8]].concat();
+        bytes.push(Opcode::Load as u8);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        let mut vm = vm(bytes);
+        assert_eq!(vm.run(), Err(Trap::HeapOutOfBounds));
+    }
+
+    #[test]
+    fn invalid_opcode_traps_on_unknown_byte() {
+        let mut vm = vm(alloc::vec![0xff]);
+        assert_eq!(vm.run(), Err(Trap::InvalidOpcode(0xff)));
+        assert_eq!(vm.ip, 1);
+    }
+
+    #[test]
+    fn truncated_traps_when_operand_bytes_run_out() {
+        // `Jump` needs a two-byte address; only the opcode is present.
+        let mut vm = vm(alloc::vec![Opcode::Jump as u8]);
+        assert_eq!(vm.run(), Err(Trap::Truncated));
+        assert_eq!(vm.ip, 1);
     }
 }